@@ -4,9 +4,11 @@ pub mod road_network {
     use crate::road_dijkstras::*;
     use core::num;
     use osmpbfreader::objects::OsmObj;
+    use serde::{Deserialize, Serialize};
+    use sha3::{Digest, Sha3_256};
     use std::{collections::HashMap, ops::Index};
 
-    #[derive(Debug, PartialEq, Hash, Eq, Clone, Copy, PartialOrd, Ord)]
+    #[derive(Debug, PartialEq, Hash, Eq, Clone, Copy, PartialOrd, Ord, Serialize, Deserialize)]
     pub struct Node {
         //nodes from OSM, each with unique ID and coordinate position
         pub id: i64,
@@ -14,7 +16,7 @@ pub mod road_network {
         pub lon: i64,
     }
 
-    #[derive(Debug, PartialEq, Hash, Eq, Clone)]
+    #[derive(Debug, PartialEq, Hash, Eq, Clone, Serialize, Deserialize)]
     pub struct Way {
         //ways from OSM, each with unique ID, speed from highway type, and referenced nodes that it connects
         pub id: i64,
@@ -22,7 +24,7 @@ pub mod road_network {
         pub refs: Vec<i64>,
     }
 
-    #[derive(Debug, PartialEq, Clone)]
+    #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
     pub struct RoadNetwork {
         //graph struct that will be used to route
         pub nodes: HashMap<i64, Node>, // <node.id, node>
@@ -31,6 +33,24 @@ pub mod road_network {
         pub raw_nodes: Vec<i64>,
     }
 
+    //on-disk blob: the network plus a SHA3 hash of its node/edge data. `load` recomputes
+    //the hash from the deserialized graph and rejects the blob if the two differ; the
+    //hash is not compared against any other graph or input file
+    #[derive(Debug, Serialize, Deserialize)]
+    struct CachedRoadNetwork {
+        graph: RoadNetwork,
+        integrity_hash: [u8; 32],
+    }
+
+    //source-indexed settled-distance table produced by running dijkstra(source, -1, ..)
+    //to settle every reachable node once
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct PrecomputedDistanceTable {
+        pub source_id: i64,
+        pub distances: HashMap<i64, u64>,
+        integrity_hash: [u8; 32],
+    }
+
     fn speed_calc(highway: &str) -> Option<u64> {
         //calculates speed of highway based on given values
         match highway {
@@ -173,7 +193,26 @@ pub mod road_network {
             {
                 counter += 1;
                 let mut shortest_path_graph = RoadDijkstra::new(&self);
-                shortest_path_graph.dijkstra(source_id, -1, &None, false);
+                let mut report_progress = |state: SearchState| {
+                    println!(
+                        "lcc sweep {counter}: settled {} ({:.1}% of graph)",
+                        state.settled,
+                        state.frac_done * 100.0
+                    );
+                    true
+                };
+                //report progress roughly 20 times over the sweep, down to every node
+                //for tiny graphs, rather than a fixed interval that never fires below it
+                let progress_interval = (self.nodes.len() / 20).max(1);
+                shortest_path_graph.search(
+                    source_id,
+                    -1,
+                    &None,
+                    false,
+                    SearchMode::Dijkstra,
+                    Some(&mut report_progress),
+                    progress_interval,
+                );
                 for node in shortest_path_graph.visited_nodes.keys() {
                     number_times_node_visted.insert(*node, counter);
                 }
@@ -205,6 +244,290 @@ pub mod road_network {
 
             RoadNetwork::new(lcc_nodes, self.raw_ways)
         }
+
+        //SHA3 hash over the node/edge data, used to detect a stale cache on load
+        fn integrity_hash(&self) -> [u8; 32] {
+            let mut hasher = Sha3_256::new();
+            let mut node_ids: Vec<&i64> = self.nodes.keys().collect();
+            node_ids.sort();
+            for id in node_ids {
+                let node = self.nodes.get(id).unwrap();
+                hasher.update(node.id.to_le_bytes());
+                hasher.update(node.lat.to_le_bytes());
+                hasher.update(node.lon.to_le_bytes());
+            }
+            let mut tail_ids: Vec<&i64> = self.edges.keys().collect();
+            tail_ids.sort();
+            for tail in tail_ids {
+                let heads = self.edges.get(tail).unwrap();
+                let mut head_ids: Vec<&i64> = heads.keys().collect();
+                head_ids.sort();
+                for head in head_ids {
+                    let (cost, flag) = heads.get(head).unwrap();
+                    hasher.update(tail.to_le_bytes());
+                    hasher.update(head.to_le_bytes());
+                    hasher.update(cost.to_le_bytes());
+                    hasher.update([*flag as u8]);
+                }
+            }
+            hasher.finalize().into()
+        }
+
+        //serializes this graph to disk via bincode, alongside a hash of its contents
+        pub fn save(&self, path: &str) -> std::io::Result<()> {
+            let cached = CachedRoadNetwork {
+                graph: self.clone(),
+                integrity_hash: self.integrity_hash(),
+            };
+            let bytes = bincode::serialize(&cached)
+                .unwrap_or_else(|e| panic!("failed to serialize RoadNetwork: {e}"));
+            std::fs::write(path, bytes)
+        }
+
+        //reloads a graph saved with `save`, rejecting the cache if its stored hash no
+        //longer matches the deserialized node/edge data
+        pub fn load(path: &str) -> std::io::Result<Option<Self>> {
+            let bytes = std::fs::read(path)?;
+            let cached: CachedRoadNetwork = bincode::deserialize(&bytes)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+            if cached.graph.integrity_hash() != cached.integrity_hash {
+                return Ok(None);
+            }
+            Ok(Some(cached.graph))
+        }
+
+        //settles every node reachable from `source_id` and writes the distance table to
+        //disk, so repeat queries from the same source skip re-running dijkstra
+        pub fn precompute_from(&self, source_id: i64, path: &str) -> std::io::Result<()> {
+            let mut shortest_path_graph = RoadDijkstra::new(self);
+            shortest_path_graph.dijkstra(source_id, -1, &None, false);
+            let table = PrecomputedDistanceTable {
+                source_id,
+                distances: shortest_path_graph.visited_nodes.clone(),
+                integrity_hash: self.integrity_hash(),
+            };
+            let bytes = bincode::serialize(&table)
+                .unwrap_or_else(|e| panic!("failed to serialize distance table: {e}"));
+            std::fs::write(path, bytes)
+        }
+
+        //reloads a distance table written by `precompute_from`, rejecting it if the
+        //current graph no longer matches the hash stored alongside it
+        pub fn load_precomputed(&self, path: &str) -> std::io::Result<Option<PrecomputedDistanceTable>> {
+            let bytes = std::fs::read(path)?;
+            let table: PrecomputedDistanceTable = bincode::deserialize(&bytes)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+            if table.integrity_hash != self.integrity_hash() {
+                return Ok(None);
+            }
+            Ok(Some(table))
+        }
+
+        //all-pairs shortest paths via Floyd-Warshall over a compacted node indexing.
+        //only practical for a few thousand nodes (the matrices are O(n^2)); returns an
+        //error instead of silently blowing up memory if `self.nodes` exceeds `max_nodes`.
+        pub fn all_pairs_shortest_paths(
+            &self,
+            max_nodes: usize,
+        ) -> Result<AllPairsShortestPaths, String> {
+            let n = self.nodes.len();
+            if n > max_nodes {
+                return Err(format!(
+                    "all_pairs_shortest_paths: {n} nodes exceeds the configured cap of {max_nodes}"
+                ));
+            }
+
+            let index_to_node: Vec<i64> = self.nodes.keys().copied().collect();
+            let node_to_index: HashMap<i64, usize> = index_to_node
+                .iter()
+                .enumerate()
+                .map(|(i, &id)| (id, i))
+                .collect();
+
+            const INF: u64 = u64::MAX;
+            let mut dist = vec![vec![INF; n]; n];
+            let mut pred: Vec<Vec<Option<usize>>> = vec![vec![None; n]; n];
+            for (i, row) in dist.iter_mut().enumerate().take(n) {
+                row[i] = 0;
+            }
+            for (&tail_id, heads) in &self.edges {
+                let Some(&i) = node_to_index.get(&tail_id) else {
+                    continue;
+                };
+                for (&head_id, &(cost, _)) in heads {
+                    let Some(&j) = node_to_index.get(&head_id) else {
+                        continue;
+                    };
+                    if cost < dist[i][j] {
+                        dist[i][j] = cost;
+                        pred[i][j] = Some(i);
+                    }
+                }
+            }
+
+            for k in 0..n {
+                for i in 0..n {
+                    if dist[i][k] == INF {
+                        continue;
+                    }
+                    for j in 0..n {
+                        if dist[k][j] == INF {
+                            continue;
+                        }
+                        //guard against overflow on the addition before comparing
+                        if let Some(through_k) = dist[i][k].checked_add(dist[k][j]) {
+                            if through_k < dist[i][j] {
+                                dist[i][j] = through_k;
+                                pred[i][j] = pred[k][j];
+                            }
+                        }
+                    }
+                }
+            }
+
+            Ok(AllPairsShortestPaths {
+                index_to_node,
+                node_to_index,
+                dist,
+                pred,
+            })
+        }
+    }
+
+    //result of `RoadNetwork::all_pairs_shortest_paths`: a dense distance matrix plus a
+    //predecessor matrix over a compacted 0..n node indexing
+    pub struct AllPairsShortestPaths {
+        index_to_node: Vec<i64>,
+        node_to_index: HashMap<i64, usize>,
+        dist: Vec<Vec<u64>>,
+        pred: Vec<Vec<Option<usize>>>,
+    }
+
+    impl AllPairsShortestPaths {
+        pub fn distance(&self, source_id: i64, target_id: i64) -> Option<u64> {
+            let i = *self.node_to_index.get(&source_id)?;
+            let j = *self.node_to_index.get(&target_id)?;
+            match self.dist[i][j] {
+                u64::MAX => None,
+                cost => Some(cost),
+            }
+        }
+
+        //walks the predecessor matrix from target back to source, returning the path
+        //in source-to-target order
+        pub fn reconstruct(&self, graph: &RoadNetwork, source_id: i64, target_id: i64) -> Option<Vec<Node>> {
+            let i = *self.node_to_index.get(&source_id)?;
+            let j = *self.node_to_index.get(&target_id)?;
+            if self.dist[i][j] == u64::MAX {
+                return None;
+            }
+
+            let mut path_indices = vec![j];
+            let mut current = j;
+            while current != i {
+                current = self.pred[i][current]?;
+                path_indices.push(current);
+            }
+            path_indices.reverse();
+
+            path_indices
+                .into_iter()
+                .map(|idx| graph.nodes.get(&self.index_to_node[idx]).copied())
+                .collect()
+        }
+    }
+}
+
+#[allow(unused)]
+pub mod spatial_index {
+    //r-tree backed nearest-node / bbox lookups over RoadNetwork.nodes
+    use crate::road_network::*;
+    use rstar::{PointDistance, RTree, RTreeObject, AABB};
+
+    #[derive(Debug, Clone, Copy)]
+    pub struct IndexedNode {
+        pub id: i64,
+        pub lat: i64,
+        pub lon: i64,
+    }
+
+    impl RTreeObject for IndexedNode {
+        type Envelope = AABB<[i64; 2]>;
+
+        fn envelope(&self) -> Self::Envelope {
+            AABB::from_point([self.lat, self.lon])
+        }
+    }
+
+    impl PointDistance for IndexedNode {
+        //squared distance to the query point; rstar only needs relative ordering, so
+        //there's no need to take a (lossy, i64) square root here. Coordinates are raw
+        //scaled lat/lon (up to ~1.8e9 in magnitude), so a pair straddling the
+        //antimeridian can square-and-sum past i64::MAX -- widen to i128 before doing
+        //the arithmetic and saturate back down rather than overflow/wrap.
+        fn distance_2(&self, point: &[i64; 2]) -> i64 {
+            let d_lat = self.lat as i128 - point[0] as i128;
+            let d_lon = self.lon as i128 - point[1] as i128;
+            let squared = d_lat * d_lat + d_lon * d_lon;
+            squared.min(i64::MAX as i128) as i64
+        }
+    }
+
+    pub struct NodeIndex {
+        tree: RTree<IndexedNode>,
+    }
+
+    impl NodeIndex {
+        //builds an r-tree over every node in the graph, keyed by scaled i64 lat/lon
+        pub fn new(graph: &RoadNetwork) -> Self {
+            let points = graph
+                .nodes
+                .values()
+                .map(|node| IndexedNode {
+                    id: node.id,
+                    lat: node.lat,
+                    lon: node.lon,
+                })
+                .collect::<Vec<IndexedNode>>();
+            Self {
+                tree: RTree::bulk_load(points),
+            }
+        }
+
+        //nearest graph node to an arbitrary (lat, lon) query, O(log n)
+        pub fn nearest_node(&self, lat: f64, lon: f64) -> Option<i64> {
+            let query = [
+                (lat * f64::powi(10.0, 7)) as i64,
+                (lon * f64::powi(10.0, 7)) as i64,
+            ];
+            self.tree
+                .nearest_neighbor(&query)
+                .map(|indexed| indexed.id)
+        }
+
+        //all node ids whose coordinates fall within the given lat/lon bounding box
+        pub fn nodes_in_bbox(
+            &self,
+            lat_min: f64,
+            lat_max: f64,
+            lon_min: f64,
+            lon_max: f64,
+        ) -> Vec<i64> {
+            let envelope = AABB::from_corners(
+                [
+                    (lat_min * f64::powi(10.0, 7)) as i64,
+                    (lon_min * f64::powi(10.0, 7)) as i64,
+                ],
+                [
+                    (lat_max * f64::powi(10.0, 7)) as i64,
+                    (lon_max * f64::powi(10.0, 7)) as i64,
+                ],
+            );
+            self.tree
+                .locate_in_envelope(&envelope)
+                .map(|indexed| indexed.id)
+                .collect()
+        }
     }
 }
 
@@ -212,6 +535,7 @@ pub mod road_network {
 pub mod road_dijkstras {
     //routing algorithms and helper functiions
     use crate::road_network::*;
+    use crate::spatial_index::NodeIndex;
     use rand::Rng;
     use std::cmp::Reverse;
     use std::collections::{BinaryHeap, HashMap, HashSet};
@@ -220,12 +544,35 @@ pub mod road_dijkstras {
     use std::rc::Rc;
     use std::time::Instant;
 
+    //sensible default cadence for callers of `search` that don't care about progress
+    //reporting cadence (e.g. `dijkstra`, which passes no callback at all)
+    const DEFAULT_PROGRESS_INTERVAL: usize = 1000;
+
     pub struct RoadDijkstra {
         //handle dijkstra calculations
         pub graph: RoadNetwork,
         pub visited_nodes: HashMap<i64, u64>,
         cost_upper_bound: u64,
         max_settled_nodes: u64,
+        spatial_index: Option<NodeIndex>,
+        landmarks: Option<LandmarkHeuristic>,
+    }
+
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    pub enum SearchMode {
+        Bfs,     //edges are treated as unit cost
+        Greedy,  //priority is the heuristic alone; fast, not optimal
+        Dijkstra, //priority is g alone; ignores the heuristic
+        AStar,   //priority is g + h
+    }
+
+    //snapshot of search progress, reported to a caller-supplied progress callback
+    #[derive(Debug, PartialEq, Clone, Copy)]
+    pub struct SearchState {
+        pub settled: usize,
+        pub queue_size: usize,
+        pub best_cost: u64,
+        pub frac_done: f64,
     }
 
     #[derive(Debug, PartialEq, Clone, Eq, PartialOrd, Ord, Hash)]
@@ -259,6 +606,8 @@ pub mod road_dijkstras {
         }
     }
 
+    //raw euclidean-distance/max-speed lower bound; cheap to build but loose -- prefer
+    //`LandmarkHeuristic` below when query volume justifies the landmark precompute
     pub fn a_star_heuristic(graph: &RoadNetwork, target: i64) -> HashMap<i64, u64> {
         let tail = *graph.nodes.get(&target).unwrap();
         //for each current i64 id, enter euciladan distance from current to target, divided by max speed on that path
@@ -280,6 +629,74 @@ pub mod road_dijkstras {
         heuristics
     }
 
+    //ALT (A*, Landmarks, Triangle inequality) heuristic: a tighter admissible lower
+    //bound than raw_euclidean / max_speed, built from a handful of precomputed
+    //landmark-to-everywhere distance tables
+    #[derive(Clone)]
+    pub struct LandmarkHeuristic {
+        //dist(landmark, v) for every reachable v, one table per landmark
+        landmark_distances: Vec<HashMap<i64, u64>>,
+    }
+
+    impl LandmarkHeuristic {
+        //picks k landmarks by farthest-point selection (each new landmark maximizes its
+        //distance to the *nearest* already-chosen landmark, so picks spread out rather
+        //than cluster) and runs a full dijkstra from each. Landmark distances are
+        //computed on the largest connected component -- not the graph as handed in --
+        //so every node that can actually get a finite entry does.
+        pub fn new(graph: &RoadNetwork, num_landmarks: usize) -> Self {
+            let lcc = graph.clone().reduce_to_largest_connected_component();
+            let mut shortest_path_graph = RoadDijkstra::new(&lcc);
+            let mut landmark_distances: Vec<HashMap<i64, u64>> = Vec::new();
+
+            let first_landmark = *lcc.raw_nodes.first().unwrap_or(&0);
+            let (_, _) = shortest_path_graph.dijkstra(first_landmark, -1, &None, false);
+            landmark_distances.push(shortest_path_graph.visited_nodes.clone());
+
+            for _ in 1..num_landmarks {
+                //next landmark maximizes its distance to the nearest landmark already
+                //chosen, so new picks spread across the component instead of clustering
+                let next_landmark = lcc
+                    .raw_nodes
+                    .iter()
+                    .max_by_key(|id| {
+                        landmark_distances
+                            .iter()
+                            .map(|table| *table.get(id).unwrap_or(&0))
+                            .min()
+                            .unwrap_or(0)
+                    })
+                    .copied();
+                let Some(next_landmark) = next_landmark else {
+                    break;
+                };
+                let (_, _) = shortest_path_graph.dijkstra(next_landmark, -1, &None, false);
+                landmark_distances.push(shortest_path_graph.visited_nodes.clone());
+            }
+
+            Self { landmark_distances }
+        }
+
+        //admissible lower bound for every node v toward `target`: max over landmarks L
+        //of |dist(L,v) - dist(L,t)|, valid by the triangle inequality
+        pub fn build_target_heuristic(&self, target: i64) -> HashMap<i64, u64> {
+            let mut heuristic: HashMap<i64, u64> = HashMap::new();
+            for table in &self.landmark_distances {
+                let Some(&dist_to_target) = table.get(&target) else {
+                    continue;
+                };
+                for (&node, &dist_to_node) in table {
+                    let bound = dist_to_node.abs_diff(dist_to_target);
+                    heuristic
+                        .entry(node)
+                        .and_modify(|h| *h = (*h).max(bound))
+                        .or_insert(bound);
+                }
+            }
+            heuristic
+        }
+    }
+
     impl RoadDijkstra {
         //implementation of dijkstra's shortest path algorithm
         pub fn new(graph: &RoadNetwork) -> Self {
@@ -289,7 +706,44 @@ pub mod road_dijkstras {
                 visited_nodes,
                 cost_upper_bound: u64::MAX,
                 max_settled_nodes: u64::MAX,
+                spatial_index: None,
+                landmarks: None,
+            }
+        }
+
+        //lazily builds (and caches) the r-tree over this graph's nodes
+        fn spatial_index(&mut self) -> &NodeIndex {
+            if self.spatial_index.is_none() {
+                self.spatial_index = Some(NodeIndex::new(&self.graph));
+            }
+            self.spatial_index.as_ref().unwrap()
+        }
+
+        //lazily builds (and caches) the ALT landmark tables over this graph, so
+        //repeated calls -- e.g. successive `multi_waypoint_route` trip requests --
+        //don't each re-run `num_landmarks` full dijkstra sweeps to rebuild them
+        fn landmarks(&mut self, num_landmarks: usize) -> &LandmarkHeuristic {
+            if self.landmarks.is_none() {
+                self.landmarks = Some(LandmarkHeuristic::new(&self.graph, num_landmarks));
             }
+            self.landmarks.as_ref().unwrap()
+        }
+
+        //maps an arbitrary (lat, lon) query onto the nearest graph node id
+        pub fn nearest_node(&mut self, lat: f64, lon: f64) -> Option<i64> {
+            self.spatial_index().nearest_node(lat, lon)
+        }
+
+        //all node ids within a lat/lon bounding box
+        pub fn nodes_in_bbox(
+            &mut self,
+            lat_min: f64,
+            lat_max: f64,
+            lon_min: f64,
+            lon_max: f64,
+        ) -> Vec<i64> {
+            self.spatial_index()
+                .nodes_in_bbox(lat_min, lat_max, lon_min, lon_max)
         }
 
         pub fn set_cost_upper_bound(&mut self, upper_bound: u64) {
@@ -331,7 +785,60 @@ pub mod road_dijkstras {
             heuristics: &Option<HashMap<i64, u64>>,
             consider_arc_flags: bool,
         ) -> (Option<RoadPathedNode>, HashMap<i64, i64>) {
-            //Heap(distance, node), Reverse turns binaryheap into minheap (default is maxheap)
+            //ordinary dijkstra ignores the heuristic; a heuristic turns this into A*
+            let mode = match heuristics {
+                Some(_) => SearchMode::AStar,
+                None => SearchMode::Dijkstra,
+            };
+            self.search(
+                source_id,
+                target_id,
+                heuristics,
+                consider_arc_flags,
+                mode,
+                None,
+                DEFAULT_PROGRESS_INTERVAL,
+            )
+        }
+
+        //A* against a precomputed ALT landmark heuristic, a much tighter admissible
+        //bound than `a_star_heuristic`'s raw Euclidean-distance-over-max-speed estimate
+        pub fn dijkstra_alt(
+            &mut self,
+            source_id: i64,
+            target_id: i64,
+            landmarks: &LandmarkHeuristic,
+            consider_arc_flags: bool,
+        ) -> (Option<RoadPathedNode>, HashMap<i64, i64>) {
+            let heuristic = Some(landmarks.build_target_heuristic(target_id));
+            self.search(
+                source_id,
+                target_id,
+                &heuristic,
+                consider_arc_flags,
+                SearchMode::AStar,
+                None,
+                DEFAULT_PROGRESS_INTERVAL,
+            )
+        }
+
+        //mode-parameterized search generalizing dijkstra/A* into BFS and greedy
+        //best-first as well. `progress_callback`, if given, is invoked every
+        //`progress_interval` settled nodes with a snapshot of search progress;
+        //returning false aborts the search early. Callers routing small subgraphs
+        //(e.g. a bbox-filtered neighborhood) should pass a smaller interval so the
+        //callback fires at all.
+        pub fn search(
+            &mut self,
+            source_id: i64,
+            target_id: i64,
+            heuristics: &Option<HashMap<i64, u64>>,
+            consider_arc_flags: bool,
+            mode: SearchMode,
+            mut progress_callback: Option<&mut dyn FnMut(SearchState) -> bool>,
+            progress_interval: usize,
+        ) -> (Option<RoadPathedNode>, HashMap<i64, i64>) {
+            //Heap(priority, node), Reverse turns binaryheap into minheap (default is maxheap)
             let mut priority_queue: BinaryHeap<Reverse<(u64, RoadPathedNode)>> = BinaryHeap::new();
             let mut previous_nodes = HashMap::new();
 
@@ -356,19 +863,7 @@ pub mod road_dijkstras {
 
             priority_queue.push(Reverse((0, source_node.clone())));
 
-            let mut target: Node = Node {
-                id: 0,
-                lon: 0,
-                lat: 0,
-            };
-            if target_id > 0 {
-                target = *self
-                    .graph
-                    .nodes
-                    .get(&target_id)
-                    .unwrap_or_else(|| panic!("target node not found"));
-            }
-
+            let total_nodes = self.graph.nodes.len().max(1);
             let mut counter = 1;
             let mut cost = 0;
             while !priority_queue.is_empty() {
@@ -378,11 +873,26 @@ pub mod road_dijkstras {
 
                 self.visited_nodes.insert(idx, cost);
 
-                //found target node
+                //found target node -- check this before the progress callback so an
+                //abort request on the same settle never throws away a completed path
                 if idx.eq(&target_id) {
                     return (Some(pathed_current_node), previous_nodes);
                 }
 
+                if let Some(callback) = progress_callback.as_deref_mut() {
+                    if progress_interval > 0 && self.visited_nodes.len().is_multiple_of(progress_interval) {
+                        let keep_going = callback(SearchState {
+                            settled: self.visited_nodes.len(),
+                            queue_size: priority_queue.len(),
+                            best_cost: cost,
+                            frac_done: self.visited_nodes.len() as f64 / total_nodes as f64,
+                        });
+                        if !keep_going {
+                            return (None, previous_nodes);
+                        }
+                    }
+                }
+
                 //stop conditions
                 //cost or # of settled nodes goes over limit
                 if cost > self.cost_upper_bound
@@ -397,7 +907,12 @@ pub mod road_dijkstras {
                 }
 
                 for neighbor in self.get_neighbors(&pathed_current_node, consider_arc_flags) {
-                    let temp_distance = pathed_current_node.distance_from_start + neighbor.1;
+                    //BFS treats every edge as unit cost rather than its real weight
+                    let edge_cost = match mode {
+                        SearchMode::Bfs => 1,
+                        _ => neighbor.1,
+                    };
+                    let temp_distance = pathed_current_node.distance_from_start + edge_cost;
                     let next_distance = *gscore.get(&neighbor.0.id).unwrap_or(&u64::MAX);
 
                     if temp_distance < next_distance {
@@ -408,14 +923,18 @@ pub mod road_dijkstras {
                             distance_from_start: temp_distance,
                             parent_node: Some(prev_node),
                         };
-                        let h;
-                        if let Some(heuristic) = heuristics {
-                            h = heuristic.get(&neighbor.0.id).unwrap_or(&0);
-                        } else {
-                            h = &0;
-                        }
-                        //fscore = temp_distance (gscore) + h (hscore)
-                        priority_queue.push(Reverse((temp_distance + h, tentative_new_node)));
+                        let h = match heuristics {
+                            Some(heuristic) => *heuristic.get(&neighbor.0.id).unwrap_or(&0),
+                            None => 0,
+                        };
+                        //priority differs by mode: BFS/Dijkstra use g alone, Greedy uses
+                        //h alone, A* uses g + h (fscore)
+                        let priority = match mode {
+                            SearchMode::Bfs | SearchMode::Dijkstra => temp_distance,
+                            SearchMode::Greedy => h,
+                            SearchMode::AStar => temp_distance + h,
+                        };
+                        priority_queue.push(Reverse((priority, tentative_new_node)));
                         previous_nodes.insert(neighbor.0.id, pathed_current_node.node_self.id);
                     }
                 }
@@ -424,6 +943,117 @@ pub mod road_dijkstras {
             (None, previous_nodes)
         }
 
+        //runs a forward search from source and a backward search from target at the
+        //same time (the graph is already stored symmetrically in `edges`, so "backward"
+        //just means starting the walk from the target instead). alternates expanding
+        //whichever frontier is smaller, and stops once the two frontiers' minimum keys
+        //sum to at least the best known meeting cost `mu`
+        pub fn bidirectional_dijkstra(
+            &mut self,
+            source_id: i64,
+            target_id: i64,
+        ) -> Option<(Vec<Node>, u64)> {
+            let mut queue_fwd: BinaryHeap<Reverse<(u64, i64)>> = BinaryHeap::new();
+            let mut queue_bwd: BinaryHeap<Reverse<(u64, i64)>> = BinaryHeap::new();
+            let mut gscore_fwd: HashMap<i64, u64> = HashMap::new();
+            let mut gscore_bwd: HashMap<i64, u64> = HashMap::new();
+            let mut parent_fwd: HashMap<i64, i64> = HashMap::new();
+            let mut parent_bwd: HashMap<i64, i64> = HashMap::new();
+            let mut settled_fwd: HashSet<i64> = HashSet::new();
+            let mut settled_bwd: HashSet<i64> = HashSet::new();
+
+            gscore_fwd.insert(source_id, 0);
+            gscore_bwd.insert(target_id, 0);
+            queue_fwd.push(Reverse((0, source_id)));
+            queue_bwd.push(Reverse((0, target_id)));
+
+            let mut mu = u64::MAX;
+            let mut meeting_node: Option<i64> = None;
+
+            while !queue_fwd.is_empty() && !queue_bwd.is_empty() {
+                let min_fwd = queue_fwd.peek().unwrap().0 .0;
+                let min_bwd = queue_bwd.peek().unwrap().0 .0;
+                if min_fwd + min_bwd >= mu {
+                    break;
+                }
+
+                //expand whichever frontier currently holds fewer queued entries
+                let expand_forward = queue_fwd.len() <= queue_bwd.len();
+                let (queue, gscore, other_gscore, parent, settled) = if expand_forward {
+                    (
+                        &mut queue_fwd,
+                        &mut gscore_fwd,
+                        &gscore_bwd,
+                        &mut parent_fwd,
+                        &mut settled_fwd,
+                    )
+                } else {
+                    (
+                        &mut queue_bwd,
+                        &mut gscore_bwd,
+                        &gscore_fwd,
+                        &mut parent_bwd,
+                        &mut settled_bwd,
+                    )
+                };
+
+                let Reverse((cost, node_id)) = queue.pop().unwrap();
+                if settled.contains(&node_id) {
+                    continue;
+                }
+                if cost > *gscore.get(&node_id).unwrap_or(&u64::MAX) {
+                    continue;
+                }
+                settled.insert(node_id);
+
+                if let Some(&other_cost) = other_gscore.get(&node_id) {
+                    let candidate = cost + other_cost;
+                    if candidate < mu {
+                        mu = candidate;
+                        meeting_node = Some(node_id);
+                    }
+                }
+
+                let neighbors = match self.graph.edges.get(&node_id) {
+                    Some(edges) => edges.clone(),
+                    None => HashMap::new(),
+                };
+                for (neighbor_id, (edge_cost, _)) in neighbors {
+                    let temp_distance = cost + edge_cost;
+                    if temp_distance < *gscore.get(&neighbor_id).unwrap_or(&u64::MAX) {
+                        gscore.insert(neighbor_id, temp_distance);
+                        parent.insert(neighbor_id, node_id);
+                        queue.push(Reverse((temp_distance, neighbor_id)));
+                    }
+                }
+            }
+
+            let meeting_node = meeting_node?;
+
+            //stitch the forward parent chain (source -> meeting_node) to the reversed
+            //backward parent chain (meeting_node -> target)
+            let mut path = vec![meeting_node];
+            let mut current = meeting_node;
+            while let Some(&parent) = parent_fwd.get(&current) {
+                path.push(parent);
+                current = parent;
+            }
+            path.reverse();
+
+            let mut current = meeting_node;
+            while let Some(&parent) = parent_bwd.get(&current) {
+                path.push(parent);
+                current = parent;
+            }
+
+            let path = path
+                .into_iter()
+                .map(|id| *self.graph.nodes.get(&id).unwrap())
+                .collect();
+
+            Some((path, mu))
+        }
+
         pub fn get_random_node_id(&mut self) -> Option<i64> {
             //returns ID of a random valid node from a graph
             let mut rng = rand::thread_rng();
@@ -441,21 +1071,18 @@ pub mod road_dijkstras {
             lon_min: f32,
             lon_max: f32,
         ) -> i64 {
-            let lat_range =
-                (lat_min * f32::powi(10.0, 7)) as i64..(lat_max * f32::powi(10.0, 7)) as i64;
-            let lon_range =
-                (lon_min * f32::powi(10.0, 7)) as i64..(lon_max * f32::powi(10.0, 7)) as i64;
-            let mut found = false;
-            let mut id = -1;
-            while (!found) {
-                if let Some(node_id) = self.get_random_node_id() {
-                    if let Some(node) = self.graph.nodes.get(&node_id) {
-                        found = lat_range.contains(&node.lat) && lon_range.contains(&node.lon);
-                        id = node_id
-                    }
-                }
+            //r-tree range query replaces the old O(n) rejection sampling loop
+            let candidates = self.nodes_in_bbox(
+                lat_min as f64,
+                lat_max as f64,
+                lon_min as f64,
+                lon_max as f64,
+            );
+            let mut rng = rand::thread_rng();
+            match candidates.is_empty() {
+                true => -1,
+                false => candidates[rng.gen_range(0..candidates.len())],
             }
-            id
         }
 
         pub fn get_unvisted_node_id(
@@ -488,5 +1115,317 @@ pub mod road_dijkstras {
                 }
             }
         }
+
+        //shortest tour from start to end visiting every waypoint; brute-forces the
+        //visiting order by lexicographic permutation of the intermediate set
+        pub fn multi_waypoint_route(
+            &mut self,
+            start_id: i64,
+            end_id: i64,
+            waypoints: Vec<i64>,
+            pin_first_last: bool,
+        ) -> Option<(Vec<Node>, u64)> {
+            //dense pairwise distance matrix between every stop (start, waypoints, end),
+            //routed with the ALT landmark heuristic rather than plain dijkstra. Cloned
+            //out of the cache so it's not holding an immutable borrow of `self` across
+            //the `&mut self` dijkstra_alt calls below; the cache still saves the
+            //landmark-building sweeps on every call after the first.
+            let landmarks = self.landmarks(8).clone();
+            let stops = [vec![start_id], waypoints.clone(), vec![end_id]].concat();
+            let mut leg_cache: HashMap<(i64, i64), (Option<RoadPathedNode>, u64)> = HashMap::new();
+            for &from in &stops {
+                for &to in &stops {
+                    if from == to {
+                        continue;
+                    }
+                    let (path, _) = self.dijkstra_alt(from, to, &landmarks, false);
+                    if let Some(pathed_node) = path {
+                        let cost = pathed_node.distance_from_start;
+                        leg_cache.insert((from, to), (Some(pathed_node), cost));
+                    }
+                }
+            }
+
+            let fixed_first = if pin_first_last && !waypoints.is_empty() {
+                Some(waypoints[0])
+            } else {
+                None
+            };
+            let fixed_last = if pin_first_last && waypoints.len() > 1 {
+                Some(waypoints[waypoints.len() - 1])
+            } else {
+                None
+            };
+
+            let middle: Vec<i64> = waypoints
+                .iter()
+                .copied()
+                .filter(|id| Some(*id) != fixed_first && Some(*id) != fixed_last)
+                .collect();
+
+            let mut best_order: Option<Vec<i64>> = None;
+            let mut best_cost = u64::MAX;
+            let mut permutation = middle.clone();
+            let mut indices: Vec<usize> = (0..permutation.len()).collect();
+
+            //exact permutation enumeration of the intermediate (non-pinned) waypoints
+            loop {
+                let order = [
+                    fixed_first.into_iter().collect::<Vec<i64>>(),
+                    permutation.clone(),
+                    fixed_last.into_iter().collect::<Vec<i64>>(),
+                ]
+                .concat();
+                let full_order = [vec![start_id], order, vec![end_id]].concat();
+
+                let mut total_cost: u64 = 0;
+                let mut valid = true;
+                for pair in full_order.windows(2) {
+                    match leg_cache.get(&(pair[0], pair[1])) {
+                        Some((_, cost)) => total_cost += cost,
+                        None => {
+                            valid = false;
+                            break;
+                        }
+                    }
+                }
+
+                if valid && total_cost < best_cost {
+                    best_cost = total_cost;
+                    best_order = Some(full_order);
+                }
+
+                if !next_permutation(&mut indices) {
+                    break;
+                }
+                permutation = indices.iter().map(|&i| middle[i]).collect();
+            }
+
+            let full_order = best_order?;
+            let mut path: Vec<Node> = Vec::new();
+            for (i, pair) in full_order.windows(2).enumerate() {
+                let (leg_path, _) = leg_cache.get(&(pair[0], pair[1]))?;
+                let (mut nodes, _) = leg_path.clone()?.get_path();
+                nodes.reverse(); //get_path returns target..source; flip to source..target
+                if i > 0 {
+                    nodes.remove(0); //already the previous leg's end node
+                }
+                path.extend(nodes);
+            }
+
+            Some((path, best_cost))
+        }
+    }
+
+    //advances `indices` to the next lexicographic permutation in place; returns false
+    //once the sequence is back to fully descending (i.e. all permutations exhausted)
+    fn next_permutation(indices: &mut [usize]) -> bool {
+        if indices.len() < 2 {
+            return false;
+        }
+        let mut i = indices.len() - 1;
+        while i > 0 && indices[i - 1] >= indices[i] {
+            i -= 1;
+        }
+        if i == 0 {
+            return false;
+        }
+        let mut j = indices.len() - 1;
+        while indices[j] <= indices[i - 1] {
+            j -= 1;
+        }
+        indices.swap(i - 1, j);
+        indices[i..].reverse();
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::road_network::{Node, RoadNetwork, Way};
+    use std::collections::HashMap;
+
+    //builds a straight chain of `n` nodes, `step` scaled-lat units apart, connected
+    //by a single way -- enough to exercise routing without needing a real .pbf file
+    fn make_line_network(n: i64, step: i64, speed: u64) -> RoadNetwork {
+        let mut nodes = HashMap::new();
+        for i in 0..n {
+            let id = i + 1;
+            nodes.insert(
+                id,
+                Node {
+                    id,
+                    lat: i * step,
+                    lon: 0,
+                },
+            );
+        }
+        let refs: Vec<i64> = (1..=n).collect();
+        RoadNetwork::new(
+            nodes,
+            vec![Way {
+                id: 1,
+                speed,
+                refs,
+            }],
+        )
+    }
+
+    //builds a `size` x `size` grid of nodes (row-major ids, 1-based), connected by one
+    //way per row and one way per column -- gives landmark selection an actual
+    //non-linear topology to spread across, unlike the single-chain `make_line_network`
+    fn make_grid_network(size: i64, step: i64, speed: u64) -> RoadNetwork {
+        let id_at = |row: i64, col: i64| row * size + col + 1;
+        let mut nodes = HashMap::new();
+        for row in 0..size {
+            for col in 0..size {
+                let id = id_at(row, col);
+                nodes.insert(
+                    id,
+                    Node {
+                        id,
+                        lat: row * step,
+                        lon: col * step,
+                    },
+                );
+            }
+        }
+        let mut ways = Vec::new();
+        for row in 0..size {
+            ways.push(Way {
+                id: row,
+                speed,
+                refs: (0..size).map(|col| id_at(row, col)).collect(),
+            });
+        }
+        for col in 0..size {
+            ways.push(Way {
+                id: size + col,
+                speed,
+                refs: (0..size).map(|row| id_at(row, col)).collect(),
+            });
+        }
+        RoadNetwork::new(nodes, ways)
+    }
+
+    mod spatial_index {
+        use crate::spatial_index::IndexedNode;
+        use rstar::PointDistance;
+
+        #[test]
+        fn distance_2_saturates_instead_of_overflowing_across_the_antimeridian() {
+            //raw scaled lon values near +/-180 degrees (*1e7): squaring and summing
+            //their difference in i64 would overflow/wrap before this fix
+            let a = IndexedNode {
+                id: 1,
+                lat: 0,
+                lon: 1_800_000_000,
+            };
+            let b = IndexedNode {
+                id: 2,
+                lat: 0,
+                lon: -1_800_000_000,
+            };
+            let d = a.distance_2(&[b.lat, b.lon]);
+            assert!(d >= 0);
+            assert_eq!(d, i64::MAX);
+        }
+
+        #[test]
+        fn distance_2_orders_nearer_points_before_farther_ones() {
+            let origin = IndexedNode {
+                id: 0,
+                lat: 0,
+                lon: 0,
+            };
+            let near = origin.distance_2(&[10, 10]);
+            let far = origin.distance_2(&[1_000, 1_000]);
+            assert!(near < far);
+        }
+    }
+
+    mod bidirectional_dijkstra {
+        use super::make_line_network;
+        use crate::road_dijkstras::RoadDijkstra;
+
+        #[test]
+        fn matches_unidirectional_dijkstra_cost_and_endpoints() {
+            let graph = make_line_network(6, 100_000, 50);
+
+            let mut forward_only = RoadDijkstra::new(&graph);
+            let (path, _) = forward_only.dijkstra(1, 6, &None, false);
+            let expected_cost = path.unwrap().distance_from_start;
+
+            let mut bidirectional = RoadDijkstra::new(&graph);
+            let (path, cost) = bidirectional.bidirectional_dijkstra(1, 6).unwrap();
+            assert_eq!(cost, expected_cost);
+            assert_eq!(path.first().unwrap().id, 1);
+            assert_eq!(path.last().unwrap().id, 6);
+            assert_eq!(path.len(), 6);
+        }
+    }
+
+    mod all_pairs_shortest_paths {
+        use super::make_line_network;
+
+        //a straight 4-node chain with a uniform 100_000-unit, speed-50 hop; the
+        //RoadNetwork::new cost formula gives each hop a cost of 85, hand-checked
+        //against the same lat/lon-to-seconds conversion used to build the network
+        const HOP_COST: u64 = 85;
+
+        #[test]
+        fn distances_match_hand_checked_hop_costs() {
+            let graph = make_line_network(4, 100_000, 50);
+            let apsp = graph.all_pairs_shortest_paths(100).unwrap();
+
+            assert_eq!(apsp.distance(1, 2), Some(HOP_COST));
+            assert_eq!(apsp.distance(1, 3), Some(2 * HOP_COST));
+            assert_eq!(apsp.distance(1, 4), Some(3 * HOP_COST));
+            assert_eq!(apsp.distance(2, 4), Some(2 * HOP_COST));
+            //graph is undirected (edges are inserted both ways), so cost is symmetric
+            assert_eq!(apsp.distance(4, 1), Some(3 * HOP_COST));
+        }
+
+        #[test]
+        fn reconstruct_walks_every_intermediate_node_in_order() {
+            let graph = make_line_network(4, 100_000, 50);
+            let apsp = graph.all_pairs_shortest_paths(100).unwrap();
+
+            let path = apsp.reconstruct(&graph, 1, 4).unwrap();
+            let ids: Vec<i64> = path.iter().map(|node| node.id).collect();
+            assert_eq!(ids, vec![1, 2, 3, 4]);
+        }
+
+        #[test]
+        fn rejects_graphs_over_the_configured_cap() {
+            let graph = make_line_network(4, 100_000, 50);
+            assert!(graph.all_pairs_shortest_paths(1).is_err());
+        }
+    }
+
+    mod landmark_heuristic {
+        use super::make_grid_network;
+        use crate::road_dijkstras::{LandmarkHeuristic, RoadDijkstra};
+
+        #[test]
+        fn heuristic_never_overestimates_the_true_shortest_path() {
+            let graph = make_grid_network(4, 100_000, 50);
+            let landmarks = LandmarkHeuristic::new(&graph, 3);
+
+            //graph is undirected, so dist(target, v) == dist(v, target); settling once
+            //from `target` gives the ground truth every heuristic value must bound
+            let target = 1;
+            let mut dijkstra = RoadDijkstra::new(&graph);
+            dijkstra.dijkstra(target, -1, &None, false);
+            let heuristic = landmarks.build_target_heuristic(target);
+
+            for (&node, &true_distance) in &dijkstra.visited_nodes {
+                let h = *heuristic.get(&node).unwrap_or(&0);
+                assert!(
+                    h <= true_distance,
+                    "heuristic {h} overestimates true distance {true_distance} for node {node}"
+                );
+            }
+        }
     }
 }
\ No newline at end of file